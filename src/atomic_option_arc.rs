@@ -0,0 +1,143 @@
+// Copyright 2020 Adrian Willenbücher
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::sync::{Arc, Mutex};
+
+// `AtomicOptionArc` is the mutable counterpart to `OptionArc`, offering
+// `take`/`replace`/`swap`. Backed by a `Mutex` rather than `AtomicOptionBox`'s
+// `AtomicPtr` plus crossbeam-epoch, since `Arc`'s own refcounting already
+// makes a lock's synchronous, deterministic drop the simpler match here --
+// not an oversight.
+pub struct AtomicOptionArc<T> {
+    value: Mutex<Option<Arc<T>>>,
+}
+
+impl<T> AtomicOptionArc<T> {
+    pub fn new() -> AtomicOptionArc<T> {
+        AtomicOptionArc {
+            value: Mutex::new(None),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.value.lock().unwrap().is_some()
+    }
+
+    pub fn load(&self) -> Option<Arc<T>> {
+        self.value.lock().unwrap().clone()
+    }
+
+    pub fn take(&self) -> Option<Arc<T>> {
+        self.swap(None)
+    }
+
+    pub fn replace(&self, v: Arc<T>) -> Option<Arc<T>> {
+        self.swap(Some(v))
+    }
+
+    pub fn swap(&self, v: Option<Arc<T>>) -> Option<Arc<T>> {
+        std::mem::replace(&mut *self.value.lock().unwrap(), v)
+    }
+}
+
+impl<T> Default for AtomicOptionArc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arc_with_non_send_sync)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Indicator {
+        value: Cell<u32>,
+        drop_ctr: *const AtomicUsize,
+    }
+
+    impl Drop for Indicator {
+        fn drop(&mut self) {
+            unsafe { (*self.drop_ctr).fetch_add(1, Ordering::SeqCst); }
+        }
+    }
+
+    #[test]
+    fn load_unset() {
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        assert!(b1.load().is_none());
+    }
+
+    #[test]
+    fn load_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        b1.replace(Arc::new(Indicator {
+            value: Cell::new(13579),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let loaded = b1.load().unwrap();
+        assert_eq!(loaded.value.get(), 13579);
+    }
+
+    #[test]
+    fn take_unset() {
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        assert!(b1.take().is_none());
+    }
+
+    #[test]
+    fn take_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        b1.replace(Arc::new(Indicator {
+            value: Cell::new(24680),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let taken = b1.take().unwrap();
+        assert_eq!(taken.value.get(), 24680);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        assert!(b1.take().is_none());
+        drop(taken);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn replace() {
+        let drop_ctr_1 = AtomicUsize::new(0);
+        let drop_ctr_2 = AtomicUsize::new(0);
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        b1.replace(Arc::new(Indicator {
+            value: Cell::new(1),
+            drop_ctr: &drop_ctr_1 as *const _,
+        }));
+        let old = b1.replace(Arc::new(Indicator {
+            value: Cell::new(2),
+            drop_ctr: &drop_ctr_2 as *const _,
+        }));
+        let old_arc = old.unwrap();
+        assert_eq!(old_arc.value.get(), 1);
+        assert_eq!(drop_ctr_1.load(Ordering::Acquire), 0);
+        assert_eq!(b1.load().unwrap().value.get(), 2);
+        drop(old_arc);
+        assert_eq!(drop_ctr_1.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn swap_into_empty() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionArc<Indicator> = AtomicOptionArc::new();
+        let old = b1.swap(Some(Arc::new(Indicator {
+            value: Cell::new(9999),
+            drop_ctr: &drop_ctr as *const _,
+        })));
+        assert!(old.is_none());
+        assert_eq!(b1.load().unwrap().value.get(), 9999);
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright 2020 Adrian Willenbücher
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::alloc::{dealloc, Layout};
+use std::marker::PhantomData;
+use std::ptr::{null_mut, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crossbeam_epoch as epoch;
+
+// `AtomicOptionBox` is the mutable counterpart to `OptionBox`: where
+// `OptionBox` is write-once and hands out unguarded `&T`s, `AtomicOptionBox`
+// may be written any number of times via `take`/`replace`/`swap`, and every
+// read goes through `load`, which pins an epoch for as long as the returned
+// `Guarded` is alive. Splitting this into its own type keeps the two access
+// patterns from ever coexisting on one instance -- an unguarded `&T` handed
+// out by a write-once accessor could otherwise dangle the moment another
+// thread calls `take`/`swap`.
+pub struct AtomicOptionBox<T> {
+    ptr: AtomicPtr<T>,
+}
+
+// `take`/`replace`/`swap` take `&self` and hand the caller an owned
+// `Box<T>`, so a shared reference alone lets one thread move a value
+// another thread deposited onto itself -- `Send` therefore requires
+// `T: Send`. But unlike a `Mutex`, `load` hands out a `Guarded` (deref
+// to `&T`) without any mutual exclusion, so two threads can hold `&T`
+// into the same value at once -- `Sync` therefore also requires `T: Sync`,
+// matching `RwLock<T>: Sync where T: Send + Sync` rather than `Mutex`'s
+// weaker bound.
+unsafe impl<T: Send> Send for AtomicOptionBox<T> {}
+unsafe impl<T: Send + Sync> Sync for AtomicOptionBox<T> {}
+
+// A guarded view of the value an `AtomicOptionBox` held at the time of the
+// `load` call. Holding a `Guarded` pins the epoch for as long as it is
+// alive, which delays reclamation of the memory backing `raw`, so the
+// reference it derefs to can never dangle out from under the reader even if
+// another thread concurrently calls `take`/`replace`/`swap`. The lifetime
+// borrows the `AtomicOptionBox` itself, so the owner can't drop (and
+// synchronously deallocate) it while a `Guarded` is still outstanding.
+pub struct Guarded<'a, T> {
+    raw: *const T,
+    _guard: epoch::Guard,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> std::ops::Deref for Guarded<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.raw }
+    }
+}
+
+impl<T> AtomicOptionBox<T> {
+    pub fn new() -> AtomicOptionBox<T> {
+        AtomicOptionBox {
+            ptr: AtomicPtr::new(null_mut()),
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.ptr.load(Ordering::Acquire).is_null()
+    }
+
+    pub fn load(&self) -> Option<Guarded<'_, T>> {
+        let guard = epoch::pin();
+        let raw = self.ptr.load(Ordering::Acquire);
+        if raw.is_null() {
+            None
+        } else {
+            Some(Guarded { raw, _guard: guard, _marker: PhantomData })
+        }
+    }
+
+    pub fn take(&self) -> Option<Box<T>> {
+        self.swap(None)
+    }
+
+    pub fn replace(&self, v: Box<T>) -> Option<Box<T>> {
+        self.swap(Some(v))
+    }
+
+    pub fn swap(&self, v: Option<Box<T>>) -> Option<Box<T>> {
+        let new_raw = match v {
+            Some(b) => Box::into_raw(b),
+            None => null_mut(),
+        };
+        let guard = epoch::pin();
+        let old_raw = self.ptr.swap(new_raw, Ordering::AcqRel);
+        if old_raw.is_null() {
+            None
+        } else {
+            // Move the value out by reading it, so the caller's `Box` runs
+            // `T`'s destructor synchronously instead of whenever the epoch
+            // GC happens to reclaim it -- only the now-inert backing
+            // allocation is deferred, never `T` itself. A `load()` that
+            // observed `old_raw` before this swap keeps its epoch pinned,
+            // so the `dealloc` below can't run until that reader is done.
+            //
+            // For a zero-sized `T`, `Box::into_raw` never allocated
+            // anything (it uses a dangling sentinel pointer), so `dealloc`
+            // must not be called on it.
+            let value = unsafe {
+                let old_alloc = NonNull::new_unchecked(old_raw);
+                let value = old_alloc.as_ptr().read();
+                if std::mem::size_of::<T>() != 0 {
+                    guard.defer_unchecked(move || dealloc(
+                        old_alloc.as_ptr() as *mut u8,
+                        Layout::new::<T>(),
+                    ));
+                }
+                value
+            };
+            Some(Box::new(value))
+        }
+    }
+}
+
+impl<T> Default for AtomicOptionBox<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for AtomicOptionBox<T> {
+    fn drop(&mut self) {
+        // No need for atomics because we have a &mut reference, and no need
+        // to defer reclamation because nobody else can be pinning an epoch
+        // against this instance's pointer anymore.
+        let raw: *mut T = *self.ptr.get_mut();
+        if !raw.is_null() {
+            drop(unsafe { Box::from_raw(raw) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::AtomicUsize;
+
+    struct Indicator {
+        value: Cell<u32>,
+        drop_ctr: *const AtomicUsize,
+    }
+
+    impl Drop for Indicator {
+        fn drop(&mut self) {
+            unsafe { (*self.drop_ctr).fetch_add(1, Ordering::SeqCst); }
+        }
+    }
+
+    #[test]
+    fn load_unset() {
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        assert!(b1.load().is_none());
+    }
+
+    #[test]
+    fn load_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        b1.replace(Box::new(Indicator {
+            value: Cell::new(13579),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let guarded = b1.load().unwrap();
+        assert_eq!(guarded.value.get(), 13579);
+    }
+
+    #[test]
+    fn take_unset() {
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        assert!(b1.take().is_none());
+    }
+
+    #[test]
+    fn take_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        b1.replace(Box::new(Indicator {
+            value: Cell::new(24680),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let taken = b1.take().unwrap();
+        assert_eq!(taken.value.get(), 24680);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        assert!(b1.take().is_none());
+        drop(taken);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn replace() {
+        let drop_ctr_1 = AtomicUsize::new(0);
+        let drop_ctr_2 = AtomicUsize::new(0);
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        b1.replace(Box::new(Indicator {
+            value: Cell::new(1),
+            drop_ctr: &drop_ctr_1 as *const _,
+        }));
+        let old = b1.replace(Box::new(Indicator {
+            value: Cell::new(2),
+            drop_ctr: &drop_ctr_2 as *const _,
+        }));
+        let old_box = old.unwrap();
+        assert_eq!(old_box.value.get(), 1);
+        assert_eq!(drop_ctr_1.load(Ordering::Acquire), 0);
+        assert_eq!(b1.load().unwrap().value.get(), 2);
+        drop(old_box);
+        assert_eq!(drop_ctr_1.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn swap_into_empty() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: AtomicOptionBox<Indicator> = AtomicOptionBox::new();
+        let old = b1.swap(Some(Box::new(Indicator {
+            value: Cell::new(9999),
+            drop_ctr: &drop_ctr as *const _,
+        })));
+        assert!(old.is_none());
+        assert_eq!(b1.load().unwrap().value.get(), 9999);
+    }
+
+    #[test]
+    fn take_set_zst() {
+        let b1: AtomicOptionBox<()> = AtomicOptionBox::new();
+        b1.replace(Box::new(()));
+        assert!(b1.take().is_some());
+        assert!(b1.take().is_none());
+    }
+
+    #[test]
+    fn guarded_survives_concurrent_take() {
+        use std::sync::Barrier;
+        use std::thread;
+
+        let b1: AtomicOptionBox<u32> = AtomicOptionBox::new();
+        b1.replace(Box::new(42));
+        // Forces the reader's `load` and the other thread's `take` to
+        // overlap, so the reclamation scheme -- not mere luck -- is what
+        // keeps `guarded` valid across the concurrent `take`.
+        let barrier = Barrier::new(2);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let guarded = b1.load().unwrap();
+                barrier.wait();
+                barrier.wait();
+                assert_eq!(*guarded, 42);
+            });
+            scope.spawn(|| {
+                barrier.wait();
+                let taken = b1.take();
+                barrier.wait();
+                assert_eq!(taken.map(|b| *b), Some(42));
+            });
+        });
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2020 Adrian Willenbücher
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::ffi::c_void;
+use std::sync::Arc;
+
+use crate::option_arc::OptionArc;
+use crate::option_box::OptionBox;
+
+pub trait ForeignOwnable: Sized {
+    type Target;
+
+    fn into_foreign(self) -> *const c_void;
+
+    /// # Safety
+    ///
+    /// `ptr` must have been obtained from a matching call to `into_foreign`,
+    /// and must not already have been consumed by a previous `from_foreign`.
+    unsafe fn from_foreign(ptr: *const c_void) -> Self;
+
+    /// # Safety
+    ///
+    /// `ptr` must have been obtained from a matching call to `into_foreign`,
+    /// and must not already have been consumed by a previous `from_foreign`.
+    /// `'a` is chosen by the caller and is not tied to `ptr` by this
+    /// signature, so the caller must also ensure the returned reference does
+    /// not outlive the point at which `from_foreign` could be called on the
+    /// same `ptr` -- once that happens, any still-live reference dangles.
+    unsafe fn borrow<'a>(ptr: *const c_void) -> Option<&'a Self::Target>;
+}
+
+impl<T> ForeignOwnable for OptionBox<T> {
+    type Target = T;
+
+    fn into_foreign(self) -> *const c_void {
+        match OptionBox::into_inner(self) {
+            Some(b) => Box::into_raw(b) as *const c_void,
+            None => std::ptr::null(),
+        }
+    }
+
+    unsafe fn from_foreign(ptr: *const c_void) -> OptionBox<T> {
+        if ptr.is_null() {
+            OptionBox::new()
+        } else {
+            OptionBox::from(Some(Box::from_raw(ptr as *mut T)))
+        }
+    }
+
+    unsafe fn borrow<'a>(ptr: *const c_void) -> Option<&'a T> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(&*(ptr as *const T))
+        }
+    }
+}
+
+impl<T> ForeignOwnable for OptionArc<T> {
+    type Target = T;
+
+    fn into_foreign(self) -> *const c_void {
+        match OptionArc::into_inner(self) {
+            Some(arc) => Arc::into_raw(arc) as *const c_void,
+            None => std::ptr::null(),
+        }
+    }
+
+    unsafe fn from_foreign(ptr: *const c_void) -> OptionArc<T> {
+        if ptr.is_null() {
+            OptionArc::new()
+        } else {
+            OptionArc::from(Some(Arc::from_raw(ptr as *const T)))
+        }
+    }
+
+    unsafe fn borrow<'a>(ptr: *const c_void) -> Option<&'a T> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(&*(ptr as *const T))
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arc_with_non_send_sync)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Indicator {
+        value: Cell<u32>,
+        drop_ctr: *const AtomicUsize,
+    }
+
+    impl Drop for Indicator {
+        fn drop(&mut self) {
+            unsafe { (*self.drop_ctr).fetch_add(1, Ordering::SeqCst); }
+        }
+    }
+
+    #[test]
+    fn option_box_round_trip() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        b1.set(Box::new(Indicator {
+            value: Cell::new(12345),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+
+        let ptr = b1.into_foreign();
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        assert_eq!(unsafe { OptionBox::<Indicator>::borrow(ptr) }.unwrap().value.get(), 12345);
+
+        let b2 = unsafe { OptionBox::<Indicator>::from_foreign(ptr) };
+        assert_eq!(b2.value.get(), 12345);
+        drop(b2);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn option_box_round_trip_unset() {
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        let ptr = b1.into_foreign();
+        assert!(unsafe { OptionBox::<Indicator>::borrow(ptr) }.is_none());
+        let b2 = unsafe { OptionBox::<Indicator>::from_foreign(ptr) };
+        assert!(!b2.is_set());
+    }
+
+    #[test]
+    fn option_arc_round_trip() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        b1.set(Arc::new(Indicator {
+            value: Cell::new(54321),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+
+        let ptr = b1.into_foreign();
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        assert_eq!(unsafe { OptionArc::<Indicator>::borrow(ptr) }.unwrap().value.get(), 54321);
+
+        let b2 = unsafe { OptionArc::<Indicator>::from_foreign(ptr) };
+        assert_eq!(b2.value.get(), 54321);
+        drop(b2);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn option_arc_round_trip_unset() {
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let ptr = b1.into_foreign();
+        assert!(unsafe { OptionArc::<Indicator>::borrow(ptr) }.is_none());
+        let b2 = unsafe { OptionArc::<Indicator>::from_foreign(ptr) };
+        assert!(!b2.is_set());
+    }
+}
@@ -5,8 +5,16 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+mod atomic_option_arc;
+mod atomic_option_box;
+mod foreign_ownable;
 mod option_arc;
 mod option_box;
+mod option_weak;
 
+pub use crate::atomic_option_arc::AtomicOptionArc;
+pub use crate::atomic_option_box::AtomicOptionBox;
+pub use crate::foreign_ownable::ForeignOwnable;
 pub use crate::option_arc::OptionArc;
 pub use crate::option_box::OptionBox;
+pub use crate::option_weak::OptionWeak;
@@ -10,6 +10,8 @@ use std::ptr::{null, null_mut};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicPtr, Ordering};
 
+use crate::option_weak::OptionWeak;
+
 pub struct OptionArc<T> {
     ptr: AtomicPtr<T>,
     phantom: PhantomData<Option<Arc<T>>>,
@@ -34,6 +36,12 @@ impl<T> OptionArc<T> {
     }
 
     pub fn set(&self, v: Arc<T>) {
+        if self.try_set(v).is_err() {
+            panic!("OptionArc has already been set");
+        }
+    }
+
+    pub fn try_set(&self, v: Arc<T>) -> Result<(), Arc<T>> {
         let raw = Arc::into_raw(v);
         // Success ordering is Release so that a subsequent deref/drop creates a
         // Release-Acquire pair.
@@ -45,10 +53,87 @@ impl<T> OptionArc<T> {
             Ordering::Release,
             Ordering::Relaxed,
         ).is_err() {
-            drop(unsafe { Arc::from_raw(raw) });
-            panic!("OptionArc has already been set");
+            Err(unsafe { Arc::from_raw(raw) })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { &*raw })
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.ptr.load(Ordering::Acquire).is_null()
+    }
+
+    pub fn downgrade(&self) -> OptionWeak<T> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if raw.is_null() {
+            OptionWeak::new()
+        } else {
+            // Borrow the Arc without taking ownership of it, the same way
+            // `Clone` does, so we don't run `Arc`'s `Drop` impl on a pointer
+            // that `self` still owns.
+            let arc = std::mem::ManuallyDrop::new(unsafe { Arc::from_raw(raw) });
+            OptionWeak::from(Some(Arc::downgrade(&arc)))
+        }
+    }
+
+    pub fn get_or_init<F: FnOnce() -> Arc<T>>(&self, f: F) -> &T {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if !raw.is_null() {
+            return unsafe { &*raw };
+        }
+        let new_raw = Arc::into_raw(f()) as *mut T;
+        // Release on success publishes the newly created Arc, as in `try_set`.
+        // Unlike `try_set`, failure ordering is Acquire, which lets us safely
+        // deref the pointer the losing side of the race observes.
+        match self.ptr.compare_exchange(
+            null_mut(),
+            new_raw,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => unsafe { &*new_raw },
+            Err(existing) => {
+                drop(unsafe { Arc::from_raw(new_raw) });
+                unsafe { &*existing }
+            }
         }
     }
+
+    pub fn get_or_try_init<E, F: FnOnce() -> Result<Arc<T>, E>>(&self, f: F) -> Result<&T, E> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if !raw.is_null() {
+            return Ok(unsafe { &*raw });
+        }
+        let new_raw = Arc::into_raw(f()?) as *mut T;
+        match self.ptr.compare_exchange(
+            null_mut(),
+            new_raw,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(unsafe { &*new_raw }),
+            Err(existing) => {
+                drop(unsafe { Arc::from_raw(new_raw) });
+                Ok(unsafe { &*existing })
+            }
+        }
+    }
+
+}
+
+impl<T> Default for OptionArc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> std::ops::Deref for OptionArc<T> {
@@ -101,6 +186,7 @@ impl<T> From<Option<Arc<T>>> for OptionArc<T> {
 }
 
 #[cfg(test)]
+#[allow(clippy::arc_with_non_send_sync)]
 mod tests {
     use super::*;
     use std::cell::Cell;
@@ -291,4 +377,128 @@ mod tests {
         let b1: OptionArc<Indicator> = From::from(v);
         assert!(OptionArc::into_inner(b1).is_none());
     }
+
+    #[test]
+    fn downgrade_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        b1.set(Arc::new(Indicator {
+            value: Cell::new(24680),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let weak = b1.downgrade();
+        assert_eq!(weak.upgrade().unwrap().value.get(), 24680);
+        drop(b1);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn downgrade_unset() {
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let weak = b1.downgrade();
+        assert!(!weak.is_set());
+    }
+
+    #[test]
+    fn get_or_init_unset() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let value = b1.get_or_init(|| Arc::new(Indicator {
+            value: Cell::new(11111),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        assert_eq!(value.value.get(), 11111);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        drop(b1);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn get_or_init_already_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        b1.set(Arc::new(Indicator {
+            value: Cell::new(22222),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let value = b1.get_or_init(|| panic!("f must not be called when already set"));
+        assert_eq!(value.value.get(), 22222);
+    }
+
+    #[test]
+    fn get_or_try_init_ok() {
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let drop_ctr = AtomicUsize::new(0);
+        let result: Result<&Indicator, ()> = b1.get_or_try_init(|| Ok(Arc::new(Indicator {
+            value: Cell::new(33333),
+            drop_ctr: &drop_ctr as *const _,
+        })));
+        assert_eq!(result.unwrap().value.get(), 33333);
+    }
+
+    #[test]
+    fn get_unset() {
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        assert!(b1.get().is_none());
+        assert!(!b1.is_set());
+    }
+
+    #[test]
+    fn get_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        b1.set(Arc::new(Indicator {
+            value: Cell::new(98765),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        assert!(b1.is_set());
+        assert_eq!(b1.get().unwrap().value.get(), 98765);
+    }
+
+    #[test]
+    fn try_set_unset() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let result = b1.try_set(Arc::new(Indicator {
+            value: Cell::new(54321),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        assert!(result.is_ok());
+        assert_eq!(b1.value.get(), 54321);
+    }
+
+    #[test]
+    fn try_set_already_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        b1.set(Arc::new(Indicator {
+            value: Cell::new(1),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        let result = b1.try_set(Arc::new(Indicator {
+            value: Cell::new(2),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        assert_eq!(result.unwrap_err().value.get(), 2);
+        assert_eq!(b1.value.get(), 1);
+    }
+
+    #[test]
+    fn get_or_try_init_err() {
+        let b1: OptionArc<Indicator> = OptionArc::new();
+        let result: Result<&Indicator, &str> = b1.get_or_try_init(|| Err("failed"));
+        if let Err(e) = result {
+            assert_eq!(e, "failed");
+        } else {
+            panic!("expected Err");
+        }
+
+        // A failed `f` must leave the cell empty so a later caller can retry.
+        let drop_ctr = AtomicUsize::new(0);
+        let value = b1.get_or_init(|| Arc::new(Indicator {
+            value: Cell::new(44444),
+            drop_ctr: &drop_ctr as *const _,
+        }));
+        assert_eq!(value.value.get(), 44444);
+    }
 }
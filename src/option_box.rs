@@ -33,6 +33,12 @@ impl<T> OptionBox<T> {
     }
 
     pub fn set(&self, v: Box<T>) {
+        if self.try_set(v).is_err() {
+            panic!("OptionBox has already been set");
+        }
+    }
+
+    pub fn try_set(&self, v: Box<T>) -> Result<(), Box<T>> {
         let raw = Box::into_raw(v);
         // Success ordering is Release so that a subsequent deref/drop creates a
         // Release-Acquire pair.
@@ -44,10 +50,73 @@ impl<T> OptionBox<T> {
             Ordering::Release,
             Ordering::Relaxed,
         ).is_err() {
-            drop(unsafe { Box::from_raw(raw) });
-            panic!("OptionBox has already been set");
+            Err(unsafe { Box::from_raw(raw) })
+        } else {
+            Ok(())
         }
     }
+
+    pub fn get(&self) -> Option<&T> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { &*raw })
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.ptr.load(Ordering::Acquire).is_null()
+    }
+
+    pub fn get_or_init<F: FnOnce() -> Box<T>>(&self, f: F) -> &T {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if !raw.is_null() {
+            return unsafe { &*raw };
+        }
+        let new_raw = Box::into_raw(f());
+        // Release on success publishes the newly boxed value, as in `try_set`.
+        // Unlike `try_set`, failure ordering is Acquire, which lets us safely
+        // deref the pointer the losing side of the race observes.
+        match self.ptr.compare_exchange(
+            null_mut(),
+            new_raw,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => unsafe { &*new_raw },
+            Err(existing) => {
+                drop(unsafe { Box::from_raw(new_raw) });
+                unsafe { &*existing }
+            }
+        }
+    }
+
+    pub fn get_or_try_init<E, F: FnOnce() -> Result<Box<T>, E>>(&self, f: F) -> Result<&T, E> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if !raw.is_null() {
+            return Ok(unsafe { &*raw });
+        }
+        let new_raw = Box::into_raw(f()?);
+        match self.ptr.compare_exchange(
+            null_mut(),
+            new_raw,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(unsafe { &*new_raw }),
+            Err(existing) => {
+                drop(unsafe { Box::from_raw(new_raw) });
+                Ok(unsafe { &*existing })
+            }
+        }
+    }
+}
+
+impl<T> Default for OptionBox<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> std::ops::Deref for OptionBox<T> {
@@ -221,4 +290,107 @@ mod tests {
         let b1: OptionBox<Indicator> = From::from(v);
         assert!(OptionBox::into_inner(b1).is_none());
     }
+
+    #[test]
+    fn get_or_init_unset() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        let value = b1.get_or_init(|| Box::new(Indicator {
+            value: Cell::new(11111),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        assert_eq!(value.value.get(), 11111);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 0);
+        drop(b1);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn get_or_init_already_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        b1.set(Box::new(Indicator {
+            value: Cell::new(22222),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        let value = b1.get_or_init(|| panic!("f must not be called when already set"));
+        assert_eq!(value.value.get(), 22222);
+    }
+
+    #[test]
+    fn get_or_try_init_ok() {
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        let drop_ctr = AtomicUsize::new(0);
+        let result: Result<&Indicator, ()> = b1.get_or_try_init(|| Ok(Box::new(Indicator {
+            value: Cell::new(33333),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        })));
+        assert_eq!(result.unwrap().value.get(), 33333);
+    }
+
+    #[test]
+    fn get_unset() {
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        assert!(b1.get().is_none());
+        assert!(!b1.is_set());
+    }
+
+    #[test]
+    fn get_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        b1.set(Box::new(Indicator {
+            value: Cell::new(98765),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        assert!(b1.is_set());
+        assert_eq!(b1.get().unwrap().value.get(), 98765);
+    }
+
+    #[test]
+    fn try_set_unset() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        let result = b1.try_set(Box::new(Indicator {
+            value: Cell::new(54321),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        assert!(result.is_ok());
+        assert_eq!(b1.value.get(), 54321);
+    }
+
+    #[test]
+    fn try_set_already_set() {
+        let drop_ctr = AtomicUsize::new(0);
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        b1.set(Box::new(Indicator {
+            value: Cell::new(1),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        let result = b1.try_set(Box::new(Indicator {
+            value: Cell::new(2),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        assert_eq!(result.unwrap_err().value.get(), 2);
+        assert_eq!(b1.value.get(), 1);
+    }
+
+    #[test]
+    fn get_or_try_init_err() {
+        let b1: OptionBox<Indicator> = OptionBox::new();
+        let result: Result<&Indicator, &str> = b1.get_or_try_init(|| Err("failed"));
+        if let Err(e) = result {
+            assert_eq!(e, "failed");
+        } else {
+            panic!("expected Err");
+        }
+
+        // A failed `f` must leave the cell empty so a later caller can retry.
+        let drop_ctr = AtomicUsize::new(0);
+        let value = b1.get_or_init(|| Box::new(Indicator {
+            value: Cell::new(44444),
+            drop_ctr: Cell::new(&drop_ctr as *const _),
+        }));
+        assert_eq!(value.value.get(), 44444);
+    }
 }
@@ -0,0 +1,246 @@
+// Copyright 2020 Adrian Willenbücher
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::marker::PhantomData;
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Weak};
+
+pub struct OptionWeak<T> {
+    ptr: AtomicPtr<T>,
+    phantom: PhantomData<Option<Weak<T>>>,
+}
+
+impl<T> OptionWeak<T> {
+    pub fn new() -> OptionWeak<T> {
+        OptionWeak {
+            ptr: AtomicPtr::new(null_mut()),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn into_inner(mut v: OptionWeak<T>) -> Option<Weak<T>> {
+        let raw: *mut T = *v.ptr.get_mut();
+        std::mem::forget(v);
+        if raw.is_null() {
+            None
+        } else {
+            Some(unsafe { Weak::from_raw(raw) })
+        }
+    }
+
+    pub fn set(&self, v: Weak<T>) {
+        if self.try_set(v).is_err() {
+            panic!("OptionWeak has already been set");
+        }
+    }
+
+    pub fn try_set(&self, v: Weak<T>) -> Result<(), Weak<T>> {
+        let raw = Weak::into_raw(v);
+        // Success ordering is Release so that a subsequent upgrade/drop creates
+        // a Release-Acquire pair.
+        // Failure ordering is Relaxed, because in that case we don't do anything
+        // with the current value of self.ptr.
+        if self.ptr.compare_exchange(
+            null_mut(),
+            raw as *mut _,
+            Ordering::Release,
+            Ordering::Relaxed,
+        ).is_err() {
+            Err(unsafe { Weak::from_raw(raw) })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        !self.ptr.load(Ordering::Acquire).is_null()
+    }
+
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        if raw.is_null() {
+            None
+        } else {
+            // Borrow the weak reference without taking ownership of it, the
+            // same way `Clone` below borrows it, so we don't run `Weak`'s
+            // `Drop` impl on a pointer that `self` still owns.
+            let weak = std::mem::ManuallyDrop::new(unsafe { Weak::from_raw(raw) });
+            weak.upgrade()
+        }
+    }
+}
+
+impl<T> Default for OptionWeak<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for OptionWeak<T> {
+    fn clone(&self) -> OptionWeak<T> {
+        let raw = self.ptr.load(Ordering::Acquire);
+        let new_raw = if raw.is_null() {
+            null()
+        } else {
+            let weak = std::mem::ManuallyDrop::new(unsafe { Weak::from_raw(raw) });
+            Weak::into_raw((*weak).clone())
+        };
+        OptionWeak {
+            ptr: AtomicPtr::new(new_raw as *mut _),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for OptionWeak<T> {
+    fn drop(&mut self) {
+        // No need for atomics because we have a &mut reference.
+        let raw: *mut T = *self.ptr.get_mut();
+        if !raw.is_null() {
+            drop(unsafe { Weak::from_raw(raw) });
+        }
+    }
+}
+
+impl<T> From<Option<Weak<T>>> for OptionWeak<T> {
+    fn from(v: Option<Weak<T>>) -> OptionWeak<T> {
+        let raw = match v {
+            Some(w) => Weak::into_raw(w),
+            None => null(),
+        };
+        OptionWeak {
+            ptr: AtomicPtr::new(raw as *mut _),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::arc_with_non_send_sync)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::atomic::AtomicUsize;
+
+    struct Indicator {
+        value: Cell<u32>,
+        drop_ctr: *const AtomicUsize,
+    }
+
+    impl Drop for Indicator {
+        fn drop(&mut self) {
+            unsafe { (*self.drop_ctr).fetch_add(1, Ordering::SeqCst); }
+        }
+    }
+
+    #[test]
+    fn set_and_upgrade() {
+        let drop_ctr = AtomicUsize::new(0);
+        let arc = Arc::new(Indicator {
+            value: Cell::new(12345),
+            drop_ctr: &drop_ctr as *const _,
+        });
+
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        w1.set(Arc::downgrade(&arc));
+        assert_eq!(Arc::strong_count(&arc), 1);
+        assert_eq!(Arc::weak_count(&arc), 1);
+
+        let upgraded = w1.upgrade().unwrap();
+        assert_eq!(upgraded.value.get(), 12345);
+        assert_eq!(Arc::strong_count(&arc), 2);
+        drop(upgraded);
+        assert_eq!(Arc::strong_count(&arc), 1);
+
+        drop(arc);
+        assert_eq!(drop_ctr.load(Ordering::Acquire), 1);
+        assert!(w1.upgrade().is_none());
+    }
+
+    #[test]
+    fn upgrade_unset() {
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        assert!(w1.upgrade().is_none());
+        assert!(!w1.is_set());
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_twice() {
+        let drop_ctr = AtomicUsize::new(0);
+        let arc = Arc::new(Indicator {
+            value: Cell::new(5),
+            drop_ctr: &drop_ctr as *const _,
+        });
+
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        w1.set(Arc::downgrade(&arc));
+        w1.set(Arc::downgrade(&arc));
+    }
+
+    #[test]
+    fn into_inner() {
+        let drop_ctr = AtomicUsize::new(0);
+        let arc = Arc::new(Indicator {
+            value: Cell::new(23456),
+            drop_ctr: &drop_ctr as *const _,
+        });
+
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        w1.set(Arc::downgrade(&arc));
+        let inner = OptionWeak::into_inner(w1).unwrap();
+        assert_eq!(inner.upgrade().unwrap().value.get(), 23456);
+    }
+
+    #[test]
+    fn into_inner_unset() {
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        assert!(OptionWeak::into_inner(w1).is_none());
+    }
+
+    #[test]
+    fn clone() {
+        let drop_ctr = AtomicUsize::new(0);
+        let arc = Arc::new(Indicator {
+            value: Cell::new(15),
+            drop_ctr: &drop_ctr as *const _,
+        });
+
+        let w1: OptionWeak<Indicator> = OptionWeak::new();
+        w1.set(Arc::downgrade(&arc));
+        assert_eq!(Arc::weak_count(&arc), 1);
+
+        let w2 = w1.clone();
+        assert_eq!(Arc::weak_count(&arc), 2);
+
+        drop(w1);
+        assert_eq!(Arc::weak_count(&arc), 1);
+        drop(w2);
+        assert_eq!(Arc::weak_count(&arc), 0);
+    }
+
+    #[test]
+    fn from_some() {
+        let drop_ctr = AtomicUsize::new(0);
+        let arc = Arc::new(Indicator {
+            value: Cell::new(34567),
+            drop_ctr: &drop_ctr as *const _,
+        });
+
+        let v: Option<Weak<Indicator>> = Some(Arc::downgrade(&arc));
+        let w1: OptionWeak<Indicator> = From::from(v);
+        assert_eq!(w1.upgrade().unwrap().value.get(), 34567);
+    }
+
+    #[test]
+    fn from_none() {
+        let v: Option<Weak<Indicator>> = None;
+        let w1: OptionWeak<Indicator> = From::from(v);
+        assert!(OptionWeak::into_inner(w1).is_none());
+    }
+}